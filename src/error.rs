@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Crate-wide error type. Each variant is a failure a user can plausibly
+/// hit (a malformed data/config file, a bad CLI argument, a Hyprland IPC
+/// hiccup) and `main` decides, per-variant, whether to just report it or
+/// try to recover.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("data file at {} doesn't exist (run 'init' first)", path.display())]
+    MissingData { path: PathBuf },
+
+    #[error("data file at {} is corrupt: {source}", path.display())]
+    InvalidData {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("config file at {} is corrupt: {source}", path.display())]
+    InvalidConfig {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    #[error("'input:kb_layout' is set to a non-string value; check your hyprland.conf")]
+    InvalidLayoutOption,
+
+    #[error("'{0}' is not a currently available keyboard name (see 'hyprctl devices')")]
+    InvalidDeviceName(String),
+
+    #[error("keypress duration {0} is out of the allowed range [0.2, 1.0]")]
+    InvalidDuration(f64),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Hyprland(#[from] hyprland::shared::HyprError),
+}
+
+/// Whether a failure is worth offering the user a recovery path for, versus
+/// just reporting it and giving up.
+impl Error {
+    pub fn missing_or_invalid_data_path(&self) -> Option<&PathBuf> {
+        match self {
+            Error::MissingData { path } => Some(path),
+            Error::InvalidData { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -5,55 +5,85 @@ use hyprland::{
     data::Devices,
     keyword::{Keyword, OptionValue},
     shared::HyprData,
-    Result,
 };
 
 use serde::{Deserialize, Serialize};
-use std::{future::Future, time::UNIX_EPOCH};
+use std::{collections::HashMap, future::Future, time::UNIX_EPOCH};
 
-mod data;
+use crate::error::{Error, Result};
 
-#[derive(Serialize, Deserialize)]
+mod config;
+mod daemon;
+mod data;
+mod pattern;
+mod status;
+mod watch;
+
+/// Volatile switching state: reconstructed by 'init', then mutated by every
+/// 'switch' press. Durable preferences (device patterns, keypress duration,
+/// layout order) live in [`config::Config`] instead.
+///
+/// Each device keeps its own [`DeviceState`], since two keyboards (e.g. a
+/// laptop's internal one and an external board) commonly end up on
+/// different layouts. Genuinely independent tracking needs 'daemon': it
+/// reads evdev directly, so it knows which physical device produced a
+/// given press and only advances that one. 'switch' gets no such
+/// information from Hyprland and advances every pattern-matched device
+/// identically on each call, so devices only diverge under 'switch' alone
+/// if one of them temporarily stops matching (e.g. unplugged).
+#[derive(Serialize, Deserialize, Default)]
 struct Data {
-    devices: Vec<String>,
-    last_time: f64,
-    layouts: Vec<usize>,
-    cur_freq: usize,
-    cur_all: usize,
-    sum_time: f64,
-    counter: u8,
-
     #[serde(default)]
-    max_duration: Duration,
+    devices: HashMap<String, DeviceState>,
+
+    /// Pre-per-device state, recovered by `data::load`'s migration when
+    /// reading a data file written before devices were tracked separately.
+    /// Used to seed every device first touched after the migration (until
+    /// the data is next reloaded), and itself left untouched; it is never
+    /// serialized back out.
+    #[serde(skip)]
+    legacy: Option<DeviceState>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct Duration(f64);
-
-impl Duration {
-    const DEFAULT_MAX_DURATION: f64 = 0.4;
-    const MIN: f64 = 0.2;
-    const MAX: f64 = 1.0;
-
-    fn satisfies(&self, time: f64) -> bool {
-        time < self.0
-    }
-
-    fn valid(time: f64) -> bool {
-        (Self::MIN..=Self::MAX).contains(&time)
-    }
-}
-
-impl Default for Duration {
-    fn default() -> Self {
-        Duration(Self::DEFAULT_MAX_DURATION)
+impl Data {
+    /// Returns the entry for `name`, creating one if this is the first time
+    /// it's seen. A newly created entry is seeded from the migrated legacy
+    /// state if there is one, otherwise from any already-known device (so a
+    /// hotplugged device starts on the same layout set), otherwise
+    /// `default_layouts` (since `DeviceState::default`'s empty `layouts`
+    /// would leave the very first device this data file ever tracks with
+    /// nothing to index into).
+    fn device_state_mut(&mut self, name: &str, default_layouts: &[usize]) -> &mut DeviceState {
+        if !self.devices.contains_key(name) {
+            let seed = self
+                .legacy
+                .clone()
+                .or_else(|| self.devices.values().next().cloned())
+                .unwrap_or_else(|| DeviceState {
+                    layouts: default_layouts.to_vec(),
+                    ..Default::default()
+                });
+            self.devices.insert(name.to_string(), seed);
+        }
+        self.devices.get_mut(name).expect("just inserted above")
     }
 }
 
-impl std::fmt::Display for Duration {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
-    }
+/// Per-device switching state; see [`Data`].
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct DeviceState {
+    #[serde(default)]
+    last_time: f64,
+    #[serde(default)]
+    layouts: Vec<usize>,
+    #[serde(default)]
+    cur_freq: usize,
+    #[serde(default)]
+    cur_all: usize,
+    #[serde(default)]
+    sum_time: f64,
+    #[serde(default)]
+    counter: u8,
 }
 
 /// Simple program, which can switch keyboard layout more comfotrable
@@ -61,14 +91,23 @@ impl std::fmt::Display for Duration {
 #[derive(Parser, Debug)]
 #[command(version, about, name = env!("CARGO_PKG_NAME"))]
 pub enum KbSwitcherCmd {
-    /// Initializes storage data with device names.
+    /// Initializes config and storage data with device name patterns.
     ///
-    /// Also captures current time, loads layouts from hyprland.conf,
-    /// and stores in file named "data", which is placed at
+    /// Writes the patterns to the config file at
+    /// $XDG_CONFIG_HOME/kb_switcher/config.toml (or
+    /// $HOME/.config/kb_switcher/config.toml), and captures current time
+    /// plus layouts loaded from hyprland.conf into the runtime data file at
     /// $XDG_DATA_HOME/kb_switcher/data or $HOME/.local/share/kb_switcher/data.
     ///
+    /// Each pattern may contain '*' as a wildcard (e.g. 'eic*'), and is
+    /// matched against every currently available keyboard; 'watch' reports
+    /// as the matched set changes when keyboards are plugged and unplugged.
+    ///
+    /// Re-running this keeps any config.toml settings you already have
+    /// (keypress duration, layout order) and only updates the patterns.
+    ///
     /// Must be called before all the other commands!
-    Init { devices: Vec<String> },
+    Init { device_patterns: Vec<String> },
 
     /// Subcommand for managing devices.
     #[command(subcommand)]
@@ -83,8 +122,44 @@ pub enum KbSwitcherCmd {
     ///
     /// Switches the layouts for all devices, which you added in
     /// 'init' or 'device add' command.
+    ///
+    /// Hyprland gives this command no way to tell which physical keyboard
+    /// produced the triggering keypress, so every matched device advances
+    /// identically on each call; see [`Data`]. Use 'daemon' instead if you
+    /// need each device to track its own layout independently.
     Switch,
 
+    /// Runs as a long-lived daemon instead of being invoked once per keypress.
+    ///
+    /// Opens the configured devices directly through evdev and drives the
+    /// same double/triple-press state machine as 'switch', but timed off
+    /// each key event's kernel timestamp rather than wall-clock time, so
+    /// process-spawn and file IO latency no longer count towards the
+    /// keypress duration. Devices are only monitored, never grabbed, so
+    /// normal typing through them keeps working.
+    Daemon,
+
+    /// Watches for keyboard hotplug and keeps the managed device list in
+    /// sync with the patterns given to 'init'.
+    ///
+    /// Useful for hot-swapped, Bluetooth, or dongle keyboards, which
+    /// otherwise need a manual 'device add' every time they reappear.
+    Watch,
+
+    /// Prints the current layout of each managed device.
+    ///
+    /// Meant for status bars: pair '--json' with a Waybar/status-bar custom
+    /// module expecting `{"text":..,"tooltip":..}` lines, and '--watch' to
+    /// keep the process resident and re-print whenever 'daemon' switches a
+    /// layout, instead of printing once and exiting.
+    Status {
+        #[arg(long)]
+        json: bool,
+
+        #[arg(long)]
+        watch: bool,
+    },
+
     /// The keypress duration between two presses for activating 'switch'.
     ///
     /// 'Between two presses' means from first press and third press, after which turning to
@@ -106,20 +181,21 @@ pub enum KbSwitcherCmd {
 #[derive(Subcommand, Debug)]
 pub enum DeviceCmd {
 
-    /// Prints all stored device names.
+    /// Prints all stored device name patterns.
     List,
 
-    /// Adds a device into the data file.
+    /// Adds a device name pattern into the config file.
     ///
-    /// Note: the device name must be correct, otherwise it won't add's into file.
+    /// Note: a pattern without '*' is treated as an exact device name and
+    /// must be correct, otherwise it won't add's into file.
     /// You can get the name using command 'hyprctl devices'.
     Add {
         device_name: String
     },
 
-    /// Removes matching device from the data file.
+    /// Removes matching device name pattern from the config file.
     ///
-    /// You get the device name using command 'devices list'.
+    /// You get the pattern using command 'device list'.
     Remove {
         device_name: String
     }
@@ -136,11 +212,36 @@ impl DeviceCmd {
 }
 
 impl KbSwitcherCmd {
+    /// Runs the command, recovering once from a missing or corrupt data
+    /// file instead of giving up.
+    ///
+    /// Every data-dependent command tolerates an empty device map fine:
+    /// `Data::device_state_mut` creates entries lazily, seeding a
+    /// never-before-seen device from `default_layouts_if_needed` rather
+    /// than assuming another already-populated device exists to copy
+    /// `layouts` from. So recreating an empty data file and retrying is a
+    /// safe stand-in for re-running 'init' when the file itself is the
+    /// problem, rather than the device patterns.
+    pub async fn handle_with_recovery(&self) -> Result<()> {
+        match self.handle().await {
+            Err(error) if error.missing_or_invalid_data_path().is_some() => {
+                eprintln!("{error}; recreating an empty data file and continuing.");
+                data::init()?;
+                data::dump(&Data::default())?;
+                self.handle().await
+            }
+            result => result,
+        }
+    }
+
     pub async fn handle(&self) -> Result<()> {
         match self {
-            KbSwitcherCmd::Init { devices } => init(devices).await,
+            KbSwitcherCmd::Init { device_patterns } => init(device_patterns).await,
             KbSwitcherCmd::UpdateLayouts => update_layouts().await,
             KbSwitcherCmd::Switch => switch().await,
+            KbSwitcherCmd::Daemon => daemon::run().await,
+            KbSwitcherCmd::Watch => watch::run().await,
+            KbSwitcherCmd::Status { json, watch } => status::run(*json, *watch).await,
             KbSwitcherCmd::Device(cmd) => cmd.handle().await,
             KbSwitcherCmd::KeypressDuration { duration } => handle_keypress_duration(duration),
             KbSwitcherCmd::Completion { shell } => {
@@ -151,7 +252,7 @@ impl KbSwitcherCmd {
     }
 }
 
-async fn init(devices: &[String]) -> Result<()> {
+async fn init(device_patterns: &[String]) -> Result<()> {
     let future_layouts = Keyword::get_async("input:kb_layout");
     let available_devices = Devices::get_async();
     let time = std::time::SystemTime::now()
@@ -162,128 +263,205 @@ async fn init(devices: &[String]) -> Result<()> {
 
     let layouts = load_layouts_from_hyprconf(future_layouts).await?;
 
-    let available_keyboards: std::collections::HashSet<String> = available_devices
+    let available_keyboards: Vec<String> = available_devices
         .await?
         .keyboards
         .into_iter()
         .map(|kb| kb.name)
         .collect();
 
-    let mut used_devices = vec![];
-    for device in devices {
-        if available_keyboards.contains(device) {
-            used_devices.push(device.to_owned());
-            continue;
-        }
+    let matched_devices: Vec<String> = available_keyboards
+        .into_iter()
+        .filter(|name| pattern::matches_any(device_patterns, name))
+        .collect();
 
-        eprintln!("The keyboard name is invalid: {} (skipped).\n", device);
+    if matched_devices.is_empty() {
+        eprintln!(
+            "No currently available keyboard matched the given pattern(s): {}",
+            device_patterns.join(", ")
+        );
     }
 
-    let data = Data {
-        devices: used_devices,
-        last_time: time,
-        layouts: (0..layouts.len()).collect(),
-        cur_freq: 0,
-        cur_all: 0,
-        sum_time: 0.0,
-        counter: 0,
-        max_duration: Default::default(),
-    };
+    let mut config = config::load()?;
+    config.device_patterns = device_patterns.to_vec();
+    config::dump(&config)?;
+
+    let base_layouts = base_layouts(&config, layouts.len());
+
+    let mut data = Data::default();
+    for name in matched_devices {
+        data.devices.insert(
+            name,
+            DeviceState {
+                last_time: time,
+                layouts: base_layouts.clone(),
+                ..Default::default()
+            },
+        );
+    }
 
-    data::dump(data)?;
+    data::dump(&data)?;
     Ok(())
 }
 
 async fn update_layouts() -> Result<()> {
     let future_layouts = Keyword::get_async("input:kb_layout");
+    let config = config::load()?;
     let mut data = data::load()?;
 
     let layouts = load_layouts_from_hyprconf(future_layouts).await?;
-    data.layouts = (0..layouts.len()).collect();
-    data::dump(data)?;
+    let base_layouts = base_layouts(&config, layouts.len());
+    for state in data.devices.values_mut() {
+        state.layouts = base_layouts.clone();
+    }
+    data::dump(&data)?;
     Ok(())
 }
 
 async fn switch() -> Result<()> {
     let future_devices = Devices::get_async();
+    let config = config::load()?;
 
     let press_time = std::time::SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("UNIX epoch must be earlier than current time!")
         .as_secs_f64();
     let mut data = data::load()?;
-    compute_time_and_counter(press_time, &mut data);
-    handle_press(&mut data);
+    let default_layouts = default_layouts_if_needed(&data, &config).await?;
 
-    let layout_id = data.layouts[data.cur_freq];
-    let mut processes = vec![];
+    let mut switches = vec![];
     for keyboard in future_devices
         .await?
         .keyboards
         .into_iter()
-        .filter(|keyboard| data.devices.contains(&keyboard.name))
+        .filter(|keyboard| pattern::matches_any(&config.device_patterns, &keyboard.name))
     {
-        let data = switch_xkb_layout::SwitchXKBLayoutCmdTypes::Id(layout_id as u8);
-        processes.push(switch_xkb_layout::call_async(keyboard.name, data));
+        let state = data.device_state_mut(&keyboard.name, &default_layouts);
+        compute_time_and_counter(press_time, &config.max_duration, state);
+        handle_press(state);
+        let Some(&layout_id) = state.layouts.get(state.cur_freq) else {
+            eprintln!(
+                "No known layouts for '{}' yet (run 'init' or 'update-layouts'); skipping this press.",
+                keyboard.name
+            );
+            continue;
+        };
+        switches.push((keyboard.name, layout_id as u8));
     }
 
-    data::dump(data)?;
+    data::dump(&data)?;
 
-    for process in processes {
-        process.await?;
+    for (name, layout_id) in switches {
+        switch_layout(name, layout_id).await?;
     }
     Ok(())
 }
 
-async fn add_device(device_name: &String) -> Result<()> {
-    let future_devices = Devices::get_async();
-    let mut data = data::load()?;
+/// `config.layout_order` if set, otherwise the identity ordering implied by
+/// however many layouts are currently defined. Shared by 'init',
+/// 'update-layouts', and [`default_layouts_if_needed`] so all three agree
+/// on what a device's layout set looks like for a given config/layout
+/// count.
+fn base_layouts(config: &config::Config, layouts_len: usize) -> Vec<usize> {
+    config
+        .layout_order
+        .clone()
+        .unwrap_or_else(|| (0..layouts_len).collect())
+}
 
-    let available_keyboards = future_devices.await?.keyboards;
+/// Layouts to seed a never-before-seen device with when `data` has no
+/// other device (or migrated legacy state) to copy `layouts` from — i.e.
+/// the very first device this data file ever tracks, typically because
+/// 'init' ran before any matching keyboard was plugged in. Returns an
+/// empty (and unused) `Vec` when `data` already has something to seed
+/// from, to skip the Hyprland round trip on the common path.
+async fn default_layouts_if_needed(data: &Data, config: &config::Config) -> Result<Vec<usize>> {
+    if !data.devices.is_empty() || data.legacy.is_some() {
+        return Ok(vec![]);
+    }
 
-    if !available_keyboards
-        .iter()
-        .any(|keyboard| keyboard.name == *device_name)
+    let layouts_len = match &config.layout_order {
+        Some(_) => 0,
+        None => {
+            load_layouts_from_hyprconf(Keyword::get_async("input:kb_layout"))
+                .await?
+                .len()
+        }
+    };
+    Ok(base_layouts(config, layouts_len))
+}
+
+/// Calls the switch-layout IPC for `name`, retrying once on failure so a
+/// momentary Hyprland compositor hiccup doesn't drop the keypress.
+async fn switch_layout(name: String, layout_id: u8) -> Result<()> {
+    let target = || switch_xkb_layout::SwitchXKBLayoutCmdTypes::Id(layout_id);
+    if switch_xkb_layout::call_async(name.clone(), target())
+        .await
+        .is_ok()
     {
-        eprintln!(
-            "The given keyboard name is incorrect! Available keyboards: {}",
-            available_keyboards
-                .iter()
-                .map(|keyboard| "\n- ".to_string() + &keyboard.name)
-                .collect::<String>()
-        );
-        std::process::exit(1);
+        return Ok(());
     }
+    switch_xkb_layout::call_async(name, target()).await?;
+    Ok(())
+}
+
+async fn add_device(pattern_str: &String) -> Result<()> {
+    let future_devices = Devices::get_async();
+    let mut config = config::load()?;
 
-    data.devices.push(device_name.clone());
-    data::dump(data)?;
+    if !pattern_str.contains('*') {
+        let available_keyboards = future_devices.await?.keyboards;
+        if !available_keyboards
+            .iter()
+            .any(|keyboard| keyboard.name == *pattern_str)
+        {
+            return Err(Error::InvalidDeviceName(pattern_str.clone()));
+        }
+    }
+
+    config.device_patterns.push(pattern_str.clone());
+    config::dump(&config)?;
     Ok(())
 }
 
-fn remove_device(device_name: &String) -> Result<()> {
-    let mut data = data::load()?;
+fn remove_device(pattern_str: &String) -> Result<()> {
+    let mut config = config::load()?;
 
-    if let Some((i, _)) = data
-        .devices
+    if let Some((i, _)) = config
+        .device_patterns
         .iter()
         .enumerate()
-        .find(|(_, dev)| *dev == device_name)
+        .find(|(_, pattern)| *pattern == pattern_str)
     {
-        data.devices.remove(i);
-        data::dump(data)?;
+        config.device_patterns.remove(i);
+        config::dump(&config)?;
     }
     Ok(())
 }
 
 fn list_devices() -> Result<()> {
-    let data = data::load()?;
+    let config = config::load()?;
     println!(
-        "Current stored devices:{}",
-        data.devices
+        "Current stored device patterns:{}",
+        config
+            .device_patterns
             .iter()
-            .map(|device| "\n - ".to_string() + device)
+            .map(|pattern| "\n - ".to_string() + pattern)
             .collect::<String>()
     );
+
+    let data = data::load()?;
+    if data.devices.is_empty() {
+        return Ok(());
+    }
+
+    println!("\nCurrent per-device layout:");
+    for (name, state) in &data.devices {
+        match state.layouts.get(state.cur_freq) {
+            Some(layout_id) => println!(" - {name}: layout #{layout_id}"),
+            None => println!(" - {name}: unknown (run 'init' or 'update-layouts')"),
+        }
+    }
     Ok(())
 }
 
@@ -295,18 +473,18 @@ fn handle_keypress_duration(duration: &Option<f64>) -> Result<()> {
 }
 
 fn set_keypress_duration(&duration: &f64) -> Result<()> {
-    if !Duration::valid(duration) {
-        eprintln!("The selected keypress duration is too strange! Please, set a number from range [0.2, 1.0].\nYour selected duration: {}", duration);
-        std::process::exit(1);
+    if !config::Duration::valid(duration) {
+        return Err(Error::InvalidDuration(duration));
     }
-    let mut data = data::load()?;
-    data.max_duration = Duration(duration);
-    Ok(data::dump(data)?)
+    let mut config = config::load()?;
+    config.max_duration = config::Duration(duration);
+    config::dump(&config)?;
+    Ok(())
 }
 
 fn print_keypress_duration() -> Result<()> {
-    let data = data::load()?;
-    println!("The current max keypress duration: {}", data.max_duration);
+    let config = config::load()?;
+    println!("The current max keypress duration: {}", config.max_duration);
     Ok(())
 }
 
@@ -321,52 +499,53 @@ fn print_completion(shell: &Option<Shell>) {
     );
 }
 
-fn compute_time_and_counter(press_time: f64, data: &mut Data) {
-    let diff = press_time - data.last_time;
-    data.last_time = press_time;
+fn compute_time_and_counter(press_time: f64, max_duration: &config::Duration, state: &mut DeviceState) {
+    let diff = press_time - state.last_time;
+    state.last_time = press_time;
 
-    data.sum_time += diff;
+    state.sum_time += diff;
 
-    if data.max_duration.satisfies(data.sum_time) {
-        data.counter += 1;
+    if max_duration.satisfies(state.sum_time) {
+        state.counter += 1;
     } else {
-        data.sum_time = 0.0;
-        data.counter = 1;
+        state.sum_time = 0.0;
+        state.counter = 1;
     }
 
-    if data.counter >= 2 {
-        data.sum_time = 0.0;
+    if state.counter >= 2 {
+        state.sum_time = 0.0;
     }
 }
 
-fn handle_press(data: &mut Data) {
-    if data.counter <= 1 {
-        data.cur_freq = (data.cur_freq + 1) % 2;
+fn handle_press(state: &mut DeviceState) {
+    if state.layouts.len() < 2 {
+        return;
+    }
+
+    if state.counter <= 1 {
+        state.cur_freq = (state.cur_freq + 1) % 2;
     } else {
-        data.cur_all = if data.counter > 2 {
-            data.cur_all + 1
+        state.cur_all = if state.counter > 2 {
+            state.cur_all + 1
         } else {
             2
         };
-        data.cur_all %= data.layouts.len();
+        state.cur_all %= state.layouts.len();
 
-        if data.cur_all == data.cur_freq {
-            data.cur_all += 1;
+        if state.cur_all == state.cur_freq {
+            state.cur_all += 1;
         }
 
-        (data.layouts[data.cur_all], data.layouts[data.cur_freq]) =
-            (data.layouts[data.cur_freq], data.layouts[data.cur_all]);
+        (state.layouts[state.cur_all], state.layouts[state.cur_freq]) =
+            (state.layouts[state.cur_freq], state.layouts[state.cur_all]);
     }
 }
 
 async fn load_layouts_from_hyprconf(
-    future_layouts: impl Future<Output = Result<Keyword>>,
+    future_layouts: impl Future<Output = hyprland::Result<Keyword>>,
 ) -> Result<Vec<String>> {
     match future_layouts.await?.value {
         OptionValue::String(s) => Ok(s.split(',').map(|layout| layout.to_string()).collect()),
-        _ => {
-            eprintln!("Something went wrong during getting option input:kb_layout. The given value is another than String type. Please check your config and report it to developer.");
-            std::process::exit(1);
-        }
+        _ => Err(Error::InvalidLayoutOption),
     }
 }
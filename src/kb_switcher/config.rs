@@ -0,0 +1,138 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{io::Write, path::PathBuf};
+
+use crate::error::{Error, Result};
+
+static CONFIG_PATH: Lazy<PathBuf> = Lazy::new(|| {
+    let mut config_path = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => {
+            let mut other_path =
+                PathBuf::from(std::env::var("HOME").expect("Must be HOME env variable!"));
+            other_path.push(".config");
+            other_path
+        }
+    };
+    config_path.push("kb_switcher");
+    config_path
+});
+
+static CONFIG_STORAGE: Lazy<PathBuf> = Lazy::new(|| {
+    let mut other_path = CONFIG_PATH.clone();
+    other_path.push("config.toml");
+    other_path
+});
+
+/// Durable user preferences, read from
+/// $XDG_CONFIG_HOME/kb_switcher/config.toml or
+/// $HOME/.config/kb_switcher/config.toml.
+///
+/// Unlike `Data`, this is meant to be hand-edited, no 'init' required: a
+/// change is read the next time a command runs, except that a new
+/// `layout_order` reaches already-tracked devices only via 'update-layouts'.
+#[derive(Serialize, Deserialize)]
+pub struct Config {
+    /// Name patterns (e.g. `"eic*"`) matched against available keyboards to
+    /// decide which devices 'switch', 'daemon' and 'watch' manage.
+    #[serde(default)]
+    pub device_patterns: Vec<String>,
+
+    #[serde(default)]
+    pub max_duration: Duration,
+
+    /// Explicit MRU layout ordering to seed `DeviceState.layouts` with, overriding
+    /// the default identity order (0, 1, 2, ...) derived from
+    /// `input:kb_layout`.
+    #[serde(default)]
+    pub layout_order: Option<Vec<usize>>,
+
+    /// Evdev key code 'daemon' treats as the double/triple-press trigger.
+    /// Find a key's code by running `evtest` against the device and
+    /// pressing it, the same way `hyprctl devices` is used to find device
+    /// names for `device_patterns`.
+    #[serde(default = "default_trigger_key")]
+    pub trigger_key: u16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            device_patterns: vec![],
+            max_duration: Duration::default(),
+            layout_order: None,
+            trigger_key: default_trigger_key(),
+        }
+    }
+}
+
+/// `KEY_RIGHTALT`'s evdev code (100), used until a user picks their own:
+/// it isn't otherwise bound to anything by default.
+fn default_trigger_key() -> u16 {
+    100
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Duration(pub f64);
+
+impl Duration {
+    const DEFAULT_MAX_DURATION: f64 = 0.4;
+    const MIN: f64 = 0.2;
+    const MAX: f64 = 1.0;
+
+    pub fn satisfies(&self, time: f64) -> bool {
+        time < self.0
+    }
+
+    pub fn valid(time: f64) -> bool {
+        (Self::MIN..=Self::MAX).contains(&time)
+    }
+}
+
+impl Default for Duration {
+    fn default() -> Self {
+        Duration(Self::DEFAULT_MAX_DURATION)
+    }
+}
+
+impl std::fmt::Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Loads the config, falling back to (and persisting) [`Config::default`]
+/// when the file doesn't exist yet, instead of requiring 'init' first.
+///
+/// A malformed config file is reported but not fatal either: unlike the
+/// data file, there's no 'init' to recover it with, so this falls back to
+/// defaults rather than surfacing [`Error::InvalidConfig`] up to `main`.
+pub fn load() -> Result<Config> {
+    match std::fs::read_to_string(&*CONFIG_STORAGE) {
+        Ok(content) => Ok(toml::from_str(&content).unwrap_or_else(|source| {
+            let error = Error::InvalidConfig {
+                path: CONFIG_STORAGE.clone(),
+                source,
+            };
+            eprintln!("{error}; falling back to defaults.");
+            Config::default()
+        })),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            let config = Config::default();
+            dump(&config)?;
+            Ok(config)
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+pub fn dump(config: &Config) -> Result<()> {
+    std::fs::create_dir_all(&*CONFIG_PATH)?;
+    let mut file = std::fs::File::create(&*CONFIG_STORAGE)?;
+    file.write_all(
+        toml::to_string_pretty(config)
+            .expect("Something wrong happened when serializing Config to TOML")
+            .as_bytes(),
+    )?;
+    Ok(())
+}
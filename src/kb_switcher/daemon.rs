@@ -0,0 +1,158 @@
+use evdev::{Device, InputEventKind, Key};
+use std::sync::mpsc;
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+use crate::error::Result;
+
+use super::{
+    compute_time_and_counter, config, data, default_layouts_if_needed, handle_press, pattern,
+    switch_layout,
+};
+
+/// A trigger-key press or device loss, forwarded from a [`spawn_reader`]
+/// thread to the daemon's event loop over [`spawn_watchers`]'s receiver.
+enum TriggerEvent {
+    Press { device_name: String, press_time: f64 },
+    Dropped { device_name: String },
+}
+
+/// Runs the daemon: opens every evdev device matching `Config.device_patterns`,
+/// listens for `Config.trigger_key` presses on them, and drives the same
+/// press state machine `switch()` uses, but entirely in memory using each
+/// event's kernel timestamp instead of `SystemTime::now()`.
+///
+/// Device patterns are only resolved once, at startup: a keyboard that
+/// hotplugs in afterwards is not picked up until the daemon is restarted.
+/// Run 'watch' alongside it if you want to notice such a keyboard
+/// appearing; it won't make this daemon monitor it, but it tells you it's
+/// time to restart.
+pub async fn run() -> Result<()> {
+    let config = config::load()?;
+    let mut data = data::load()?;
+    let default_layouts = default_layouts_if_needed(&data, &config).await?;
+    let trigger_key = Key(config.trigger_key);
+
+    let (rx, monitored) = spawn_watchers(&config.device_patterns, trigger_key);
+    if monitored == 0 {
+        eprintln!("No evdev keyboard matched the configured pattern(s); nothing to monitor.");
+        return Ok(());
+    }
+
+    while let Ok(event) = rx.recv() {
+        match event {
+            TriggerEvent::Dropped { device_name } => {
+                eprintln!("Device '{device_name}' disappeared; dropping it from the daemon.");
+            }
+            TriggerEvent::Press {
+                device_name,
+                press_time,
+            } => {
+                let state = data.device_state_mut(&device_name, &default_layouts);
+                compute_time_and_counter(press_time, &config.max_duration, state);
+                handle_press(state);
+                let layout_id = state.layouts.get(state.cur_freq).copied();
+
+                // Persisted unconditionally, even when there's no known
+                // layout to switch to below: the counter/timing update
+                // above already happened and would otherwise be lost on
+                // the next restart, silently breaking double/triple-press
+                // detection across it.
+                data::dump(&data)?;
+
+                let Some(layout_id) = layout_id else {
+                    eprintln!(
+                        "No known layouts for '{device_name}' yet (run 'init' or 'update-layouts'); ignoring this press."
+                    );
+                    continue;
+                };
+
+                // Unlike `switch()`, a failed switch here can't just
+                // surface and exit: that would kill the whole long-running
+                // daemon over one transient `hyprctl` hiccup. Log it and
+                // keep going; the device's advanced state is already
+                // persisted above, so the next press continues from where
+                // this one left off instead of repeating it.
+                if let Err(error) = switch_layout(device_name.clone(), layout_id as u8).await {
+                    eprintln!("Failed to switch '{device_name}' to layout #{layout_id}: {error}");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Opens every evdev device matching `patterns` and spawns a reader thread
+/// per device (see [`spawn_reader`]), returning the shared receiver side of
+/// their channel along with how many devices were actually opened.
+fn spawn_watchers(patterns: &[String], trigger_key: Key) -> (mpsc::Receiver<TriggerEvent>, usize) {
+    let devices = open_devices(patterns);
+    let monitored = devices.len();
+
+    let (tx, rx) = mpsc::channel();
+    for (name, device) in devices {
+        spawn_reader(name, device, trigger_key, tx.clone());
+    }
+
+    (rx, monitored)
+}
+
+fn open_devices(patterns: &[String]) -> Vec<(String, Device)> {
+    evdev::enumerate()
+        .filter_map(|(_, device)| {
+            let name = device.name()?.to_string();
+            pattern::matches_any(patterns, &name).then_some((name, device))
+        })
+        .collect()
+}
+
+/// Spawns one OS thread per device file, as `rusty-keys` does, and forwards
+/// trigger-key presses (plus the dropped-device case) back to the async
+/// daemon loop over `tx`.
+///
+/// The device is only read from, never grabbed (`EVIOCGRAB` is never
+/// issued), so normal typing through it is unaffected.
+fn spawn_reader(
+    device_name: String,
+    mut device: Device,
+    trigger_key: Key,
+    tx: mpsc::Sender<TriggerEvent>,
+) {
+    thread::spawn(move || loop {
+        match device.fetch_events() {
+            Ok(events) => {
+                for event in events {
+                    let InputEventKind::Key(key) = event.kind() else {
+                        continue;
+                    };
+                    if key != trigger_key || event.value() != 1 {
+                        continue;
+                    }
+
+                    let press_time = event
+                        .timestamp()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("kernel event timestamps are UNIX timestamps")
+                        .as_secs_f64();
+
+                    if tx
+                        .send(TriggerEvent::Press {
+                            device_name: device_name.clone(),
+                            press_time,
+                        })
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+            // The device file went away (unplug, or a SYN_DROPPED the kernel
+            // couldn't recover from): stop reading and let the daemon know.
+            Err(_) => {
+                let _ = tx.send(TriggerEvent::Dropped { device_name });
+                return;
+            }
+        }
+    });
+}
@@ -1,5 +1,7 @@
 use once_cell::sync::Lazy;
-use std::{path::PathBuf, io::Write};
+use std::{io::Write, path::PathBuf};
+
+use crate::error::{Error, Result};
 
 static DATA_PATH: Lazy<PathBuf> = Lazy::new(|| {
     let mut data_path = match std::env::var("XDG_DATA_HOME") {
@@ -22,33 +24,67 @@ static DATA_STORAGE: Lazy<PathBuf> = Lazy::new(|| {
     other_path
 });
 
-pub fn init() -> std::io::Result<()> {
-    std::fs::create_dir_all(&*DATA_PATH)
+pub fn init() -> Result<()> {
+    std::fs::create_dir_all(&*DATA_PATH)?;
+    Ok(())
+}
+
+/// The data file's path, for watchers (e.g. `status --watch`) that want to
+/// notice a write without re-deriving "did a switch happen" themselves.
+pub fn storage_path() -> &'static std::path::Path {
+    &DATA_STORAGE
 }
 
-pub fn dump(data: super::Data) -> std::io::Result<()> {
+pub fn dump(data: &super::Data) -> Result<()> {
     let mut file = std::fs::File::create(&*DATA_STORAGE)?;
     file.write_all(
         serde_json::to_string(&data)
             .expect("Something wrong happened when serializes from Data to string")
             .as_bytes(),
-    )
+    )?;
+    Ok(())
 }
 
-pub fn load() -> std::io::Result<super::Data> {
-    let file = match std::fs::File::open(&*DATA_STORAGE) {
-        Ok(file) => file,
-        Err(error) => match error.kind() {
-            std::io::ErrorKind::NotFound => {
-                eprintln!(
-                    "File at {} doesn't exists!\nMaybe you need to initialize data using command 'init'.",
-                    DATA_STORAGE.to_string_lossy()
-                );
-                std::process::exit(1);
-            }
-            _ => return Err(error),
-        },
+pub fn load() -> Result<super::Data> {
+    let content = match std::fs::read_to_string(&*DATA_STORAGE) {
+        Ok(content) => content,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Err(Error::MissingData {
+                path: DATA_STORAGE.clone(),
+            })
+        }
+        Err(error) => return Err(error.into()),
     };
-    let reader = std::io::BufReader::new(file);
-    Ok(serde_json::from_reader(reader)?)
+
+    let value: serde_json::Value =
+        serde_json::from_str(&content).map_err(|source| Error::InvalidData {
+            path: DATA_STORAGE.clone(),
+            source,
+        })?;
+    migrate(value)
+}
+
+/// Reads either the current `{ "devices": { name: DeviceState } }` shape,
+/// or the flat single-state shape written before devices were tracked
+/// independently, in which case the old state seeds each device
+/// `Data::device_state_mut` creates an entry for.
+fn migrate(value: serde_json::Value) -> Result<super::Data> {
+    let is_per_device = value
+        .get("devices")
+        .is_some_and(serde_json::Value::is_object);
+
+    let as_error = |source| Error::InvalidData {
+        path: DATA_STORAGE.clone(),
+        source,
+    };
+
+    if is_per_device {
+        return serde_json::from_value(value).map_err(as_error);
+    }
+
+    let legacy = serde_json::from_value(value).map_err(as_error)?;
+    Ok(super::Data {
+        legacy: Some(legacy),
+        ..Default::default()
+    })
 }
@@ -0,0 +1,40 @@
+/// Minimal glob matching for device name patterns: only `*` is treated as a
+/// wildcard, everything else must match literally.
+///
+/// Used to match user-supplied patterns like `"eic*"` or `"AT Translated*"`
+/// against the concrete device names `hyprctl devices`/evdev report, so
+/// hot-swapped keyboards don't need an exact name up front.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let mut segments = pattern.split('*').peekable();
+    let mut rest = name;
+
+    let Some(first) = segments.next() else {
+        return true;
+    };
+    match rest.strip_prefix(first) {
+        Some(remaining) => rest = remaining,
+        None => return false,
+    }
+
+    while let Some(segment) = segments.next() {
+        if segment.is_empty() {
+            continue;
+        }
+        // The last segment of a pattern not ending in '*' is anchored to
+        // the end of the name.
+        if segments.peek().is_none() {
+            return rest.ends_with(segment);
+        }
+        match rest.find(segment) {
+            Some(index) => rest = &rest[index + segment.len()..],
+            None => return false,
+        }
+    }
+
+    pattern.ends_with('*') || rest.is_empty()
+}
+
+/// True if `name` matches any of `patterns`.
+pub fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|pattern| matches(pattern, name))
+}
@@ -0,0 +1,76 @@
+use hyprland::keyword::Keyword;
+use inotify::{Inotify, WatchMask};
+
+use crate::error::Result;
+
+use super::{data, load_layouts_from_hyprconf};
+
+/// Prints the current layout of each managed device once, and, if `watch`,
+/// keeps re-printing it whenever the data file is written.
+///
+/// Watches the data file itself rather than raw trigger-key presses: a
+/// keypress alone doesn't mean a switch happened (e.g. if `daemon` isn't
+/// running), and a second, unsynchronized evdev listener reacting to the
+/// same press `daemon` just saw could print before `daemon` finishes its
+/// own `compute_time_and_counter`/`switch_layout`/`data::dump`, showing the
+/// stale pre-switch layout. Watching the write itself avoids both.
+pub async fn run(as_json: bool, watch: bool) -> Result<()> {
+    if !watch {
+        return print_status(as_json).await;
+    }
+
+    let path = data::storage_path();
+    let dir = path.parent().expect("data file path always has a parent");
+    let file_name = path
+        .file_name()
+        .expect("data file path always has a file name");
+
+    // Registered before the initial print so a write racing this startup
+    // (e.g. daemon switching a layout right as 'status --watch' starts)
+    // is still caught, instead of being missed until some later switch.
+    let mut inotify = Inotify::init()?;
+    inotify
+        .watches()
+        .add(dir, WatchMask::CREATE | WatchMask::CLOSE_WRITE)?;
+
+    print_status(as_json).await?;
+
+    let mut buffer = [0; 1024];
+    loop {
+        let events = inotify.read_events_blocking(&mut buffer)?;
+        let data_file_written = events.into_iter().any(|event| event.name == Some(file_name));
+
+        if data_file_written {
+            print_status(as_json).await?;
+        }
+    }
+}
+
+async fn print_status(as_json: bool) -> Result<()> {
+    let layouts = load_layouts_from_hyprconf(Keyword::get_async("input:kb_layout")).await?;
+    let data = data::load()?;
+
+    for (name, state) in &data.devices {
+        let Some(&layout_id) = state.layouts.get(state.cur_freq) else {
+            continue;
+        };
+        let layout_name = layouts
+            .get(layout_id)
+            .map(String::as_str)
+            .unwrap_or("unknown");
+
+        if as_json {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "text": layout_name,
+                    "tooltip": format!("{name}: {layout_name} (#{layout_id})"),
+                })
+            );
+        } else {
+            println!("{name}: {layout_name} (#{layout_id})");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use hyprland::{data::Devices, shared::HyprData};
+use inotify::{Inotify, WatchMask};
+
+use crate::error::Result;
+
+use super::{config, config::Config, pattern};
+
+/// Watches `/dev/input` for keyboard hotplug and reports, as it happens,
+/// which devices start/stop matching `Config.device_patterns`.
+///
+/// `switch` re-queries `Devices::get_async()` on every invocation, so a
+/// hotplugged keyboard is already picked up by the very next keypress with
+/// no action needed here. `daemon` is not so lucky: it opens its evdev
+/// devices once at startup (see `daemon::run`) and never rescans, so a
+/// keyboard that appears while `daemon` is already running needs a daemon
+/// restart before it's monitored, no matter what gets printed here. This
+/// command doesn't persist anything either way; it exists to let hot-swapped,
+/// Bluetooth, or dongle keyboard owners see devices get matched without
+/// re-running 'init' or 'device add'.
+pub async fn run() -> Result<()> {
+    let config = config::load()?;
+    if config.device_patterns.is_empty() {
+        eprintln!("No device patterns configured; run 'init' or 'device add' first.");
+        return Ok(());
+    }
+
+    let mut inotify = Inotify::init()?;
+    inotify
+        .watches()
+        .add("/dev/input", WatchMask::CREATE | WatchMask::DELETE)?;
+
+    let mut known = matched_devices(&config).await?;
+    announce(&[], &known);
+
+    let mut buffer = [0; 1024];
+    loop {
+        let events = inotify.read_events_blocking(&mut buffer)?;
+
+        let touched_event_node = events.into_iter().any(|event| {
+            event
+                .name
+                .map(|name| name.to_string_lossy().starts_with("event"))
+                .unwrap_or(false)
+        });
+
+        if !touched_event_node {
+            continue;
+        }
+
+        let current = matched_devices(&config).await?;
+        announce(&known, &current);
+        known = current;
+    }
+}
+
+async fn matched_devices(config: &Config) -> Result<Vec<String>> {
+    Ok(Devices::get_async()
+        .await?
+        .keyboards
+        .into_iter()
+        .map(|keyboard| keyboard.name)
+        .filter(|name| pattern::matches_any(&config.device_patterns, name))
+        .collect())
+}
+
+fn announce(before: &[String], after: &[String]) {
+    for name in after.iter().filter(|name| !before.contains(name)) {
+        println!("Device appeared, matched: {name}");
+    }
+    for name in before.iter().filter(|name| !after.contains(name)) {
+        println!("Device vanished: {name}");
+    }
+}
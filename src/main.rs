@@ -1,3 +1,4 @@
+mod error;
 mod kb_switcher;
 use kb_switcher::KbSwitcherCmd;
 
@@ -5,7 +6,13 @@ use clap::Parser;
 use tokio;
 
 #[tokio::main]
-async fn main() -> hyprland::Result<()> {
+async fn main() -> std::process::ExitCode {
     let command = KbSwitcherCmd::parse();
-    command.process().await
+    match command.handle_with_recovery().await {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            std::process::ExitCode::FAILURE
+        }
+    }
 }